@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::sync::{Arc, Mutex};
+
+use super::{BufferAddress, BufferContents, BufferDescriptor, BufferUsages, Device, Subbuffer};
+
+struct Block {
+    buffer: Arc<super::Buffer>,
+    size: BufferAddress,
+    cursor: BufferAddress,
+}
+
+/// The key a freed region is recycled under: it must match both the size and
+/// the alignment of a later allocation request, since a region allocated at a
+/// loose alignment may not satisfy a later request for a stricter one.
+type SizeClass = (BufferAddress, BufferAddress);
+
+struct ArenaState {
+    blocks: Vec<Block>,
+    /// Regions returned via [`BufferArena::free`], bucketed by `(size,
+    /// alignment)` so [`BufferArena::allocate_raw`] only recycles a region for
+    /// a request it's actually valid for.
+    freelist: HashMap<SizeClass, Vec<(Arc<super::Buffer>, BufferAddress)>>,
+}
+
+/// A bump allocator that sub-allocates many short-lived or long-lived regions out
+/// of a small number of large backing [`Buffer`](super::Buffer)s, so callers
+/// don't need to create one `Buffer` per mesh or uniform.
+///
+/// All regions carved from the same arena share `usage`. Use
+/// [`Self::allocate`] to get a typed [`Subbuffer<T>`], [`Self::allocate_bytes`]
+/// for raw byte regions, [`Self::free`] to return a region to the arena's
+/// freelist for reuse, and [`Self::reset`] to rewind the arena for transient
+/// per-frame data.
+pub struct BufferArena {
+    device: Device,
+    label: Option<String>,
+    block_size: BufferAddress,
+    usage: BufferUsages,
+    state: Mutex<ArenaState>,
+}
+
+impl BufferArena {
+    /// Create a new arena. `block_size` is the size of each backing buffer that
+    /// will be created on demand; for best use it should be large enough to hold
+    /// many allocations, since each new block is a real `create_buffer` call.
+    pub fn new(device: &Device, block_size: BufferAddress, usage: BufferUsages) -> Self {
+        BufferArena {
+            device: device.clone(),
+            label: None,
+            block_size,
+            usage,
+            state: Mutex::new(ArenaState {
+                blocks: Vec::new(),
+                freelist: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Set the label used for backing buffers created by this arena.
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Allocate room for `count` values of `T`, returning a typed
+    /// [`Subbuffer<[T]>`] whose offset is aligned to `T`'s alignment rounded up
+    /// to [`crate::COPY_BUFFER_ALIGNMENT`].
+    ///
+    /// Callers that need a dynamic-offset uniform/storage binding alignment
+    /// instead should use [`Self::allocate_bytes`] directly with that alignment.
+    pub fn allocate<T: bytemuck::Pod>(&self, count: usize) -> Subbuffer<[T]> {
+        let alignment = super::align_to(T::ALIGN, crate::COPY_BUFFER_ALIGNMENT);
+        let size = (size_of::<T>() * count) as BufferAddress;
+        let (buffer, offset) = self.allocate_raw(size, alignment);
+        Subbuffer::from_raw_parts(buffer, offset, size, alignment)
+    }
+
+    /// Allocate `size` bytes aligned to `alignment` (e.g.
+    /// [`crate::COPY_BUFFER_ALIGNMENT`] for a plain copy source/destination, or
+    /// the device's uniform/storage dynamic-offset alignment when the region
+    /// will be bound with a dynamic offset), returning an untyped
+    /// [`Subbuffer<[u8]>`].
+    pub fn allocate_bytes(&self, size: BufferAddress, alignment: BufferAddress) -> Subbuffer<[u8]> {
+        let (buffer, offset) = self.allocate_raw(size, alignment);
+        Subbuffer::from_raw_parts(buffer, offset, size, alignment)
+    }
+
+    /// First tries to recycle a region of the same `(size, alignment)` class
+    /// from the freelist, then bump-allocates within the first block with
+    /// enough space remaining, creating a new block if none has room.
+    fn allocate_raw(
+        &self,
+        size: BufferAddress,
+        alignment: BufferAddress,
+    ) -> (Arc<super::Buffer>, BufferAddress) {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some((buffer, offset)) = state
+            .freelist
+            .get_mut(&(size, alignment))
+            .and_then(|bucket| bucket.pop())
+        {
+            return (buffer, offset);
+        }
+
+        for block in &mut state.blocks {
+            let offset = super::align_to(block.cursor, alignment);
+            if offset + size <= block.size {
+                block.cursor = offset + size;
+                return (block.buffer.clone(), offset);
+            }
+        }
+
+        let block_size = self.block_size.max(size);
+        let buffer = Arc::new(self.device.create_buffer(&BufferDescriptor {
+            label: self.label.as_deref(),
+            size: block_size,
+            usage: self.usage,
+            mapped_at_creation: false,
+        }));
+        state.blocks.push(Block {
+            buffer: buffer.clone(),
+            size: block_size,
+            cursor: size,
+        });
+
+        (buffer, 0)
+    }
+
+    /// Return a previously allocated region to the arena's freelist, so a
+    /// future allocation of the same size and alignment can reuse it instead
+    /// of bump-allocating new space.
+    ///
+    /// The alignment `subbuffer` was originally allocated at is read back from
+    /// it directly, so callers don't need to re-derive or remember it.
+    pub fn free<T: BufferContents + ?Sized>(&self, subbuffer: Subbuffer<T>) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .freelist
+            .entry((subbuffer.size(), subbuffer.alignment()))
+            .or_default()
+            .push((subbuffer.buffer_arc(), subbuffer.offset()));
+    }
+
+    /// Rewind every block's bump cursor back to the start and clear the
+    /// freelist, reusing all previously created blocks for new allocations.
+    ///
+    /// Only call this once every [`Subbuffer`] handed out so far is no longer in
+    /// use, e.g. once per frame after the GPU has finished reading last frame's
+    /// transient allocations.
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        for block in &mut state.blocks {
+            block.cursor = 0;
+        }
+        state.freelist.clear();
+    }
+}
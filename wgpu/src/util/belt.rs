@@ -0,0 +1,412 @@
+use std::fmt;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+
+use super::{
+    Buffer, BufferAddress, BufferDescriptor, BufferSize, BufferUsages, BufferViewMut,
+    CommandEncoder, Device, MapMode,
+};
+
+struct Chunk {
+    buffer: Buffer,
+    size: BufferAddress,
+    offset: BufferAddress,
+}
+
+/// Efficiently performs many buffer writes by sharing and reusing temporary buffers.
+///
+/// Internally it uses a ring-buffer of staging buffers that are sub-allocated.
+/// It is designed to handle uploading many buffers as fast as possible, so is
+/// best suited for bulk transfers from the CPU to the GPU.
+///
+/// Does not require [`Features::MAPPABLE_PRIMARY_BUFFERS`](crate::Features::MAPPABLE_PRIMARY_BUFFERS):
+/// chunks are created with only `MAP_WRITE | COPY_SRC` usage, and every write is
+/// copied from its chunk into the target buffer.
+pub struct StagingBelt {
+    chunk_size: BufferAddress,
+    /// Chunks into which we are accumulating data to be transferred.
+    active_chunks: Vec<Chunk>,
+    /// Chunks that have scheduled transfers already; they are unmapped and some
+    /// command encoder has a `copy_buffer_to_buffer` command with them as source.
+    closed_chunks: Vec<Chunk>,
+    /// Chunks that are back from the GPU and ready to be mapped for write and put
+    /// into `active_chunks`.
+    free_chunks: Vec<Chunk>,
+    /// When `recall` maps a closed chunk again, the callback for `map_async` is
+    /// not called immediately; it is only invoked once the device is polled. The
+    /// callback sends the chunk through this channel so `write_buffer` and
+    /// `recall` can learn, without blocking, which chunks have become available
+    /// again.
+    sender: mpsc::Sender<Chunk>,
+    receiver: mpsc::Receiver<Chunk>,
+}
+
+impl StagingBelt {
+    /// Create a new staging belt.
+    ///
+    /// The `chunk_size` is the unit of internal buffer allocation; writes will be
+    /// sub-allocated within chunks. Therefore, for optimal use, the chunk size
+    /// should be:
+    ///
+    /// - Large enough to contain several of the largest write operations.
+    /// - Small enough to not waste too much memory if only a small amount of
+    ///   writes happen.
+    pub fn new(chunk_size: BufferAddress) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        StagingBelt {
+            chunk_size,
+            active_chunks: Vec::new(),
+            closed_chunks: Vec::new(),
+            free_chunks: Vec::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Allocate the staging belt slice of `size` to be uploaded into the `target` buffer
+    /// at the specified offset.
+    ///
+    /// The upload will be placed into the provided command encoder. This encoder
+    /// must be submitted after the write is complete and before `device` is polled
+    /// or `recall` is called.
+    ///
+    /// `device` is the device used to create the staging belt, and must be the
+    /// same device used for the `target` buffer.
+    pub fn write_buffer(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        target: &Buffer,
+        offset: BufferAddress,
+        size: BufferSize,
+        device: &Device,
+    ) -> BufferViewMut<'_> {
+        let mut chunk = if let Some(index) = self
+            .active_chunks
+            .iter()
+            .position(|chunk| chunk.offset + size.get() <= chunk.size)
+        {
+            self.active_chunks.swap_remove(index)
+        } else {
+            self.receive_chunks(); // ensure self.free_chunks is up to date
+
+            if let Some(index) = self
+                .free_chunks
+                .iter()
+                .position(|chunk| size.get() <= chunk.size)
+            {
+                self.free_chunks.swap_remove(index)
+            } else {
+                let size = self.chunk_size.max(size.get());
+                Chunk {
+                    buffer: device.create_buffer(&BufferDescriptor {
+                        label: Some("staging"),
+                        size,
+                        usage: BufferUsages::MAP_WRITE | BufferUsages::COPY_SRC,
+                        mapped_at_creation: true,
+                    }),
+                    size,
+                    offset: 0,
+                }
+            }
+        };
+
+        encoder.copy_buffer_to_buffer(&chunk.buffer, chunk.offset, target, offset, size.get());
+        let old_offset = chunk.offset;
+        chunk.offset += size.get();
+
+        let view = chunk
+            .buffer
+            .slice(old_offset..old_offset + size.get())
+            .get_mapped_range_mut();
+
+        self.active_chunks.push(chunk);
+
+        view
+    }
+
+    /// Prepare currently mapped buffers for use in a submission.
+    ///
+    /// This must be called before the command encoder(s) used in `write_buffer`
+    /// are submitted.
+    pub fn finish(&mut self) {
+        for chunk in self.active_chunks.drain(..) {
+            chunk.buffer.unmap();
+            self.closed_chunks.push(chunk);
+        }
+    }
+
+    /// Recall all of the closed buffers back to be reused.
+    ///
+    /// This must only be called after the command encoder(s) used in `write_buffer`
+    /// are submitted. Additional calls to `write_buffer` should happen before the
+    /// next `recall`.
+    pub fn recall(&mut self) {
+        self.receive_chunks();
+
+        for chunk in self.closed_chunks.drain(..) {
+            let sender = self.sender.clone();
+            chunk.buffer.slice(..).map_async(MapMode::Write, move |_| {
+                let _ = sender.send(chunk);
+            });
+        }
+    }
+
+    /// Move all chunks that the GPU is done with (and are now mapped again)
+    /// from `receiver` to `free_chunks`.
+    fn receive_chunks(&mut self) {
+        while let Ok(mut chunk) = self.receiver.try_recv() {
+            chunk.offset = 0;
+            self.free_chunks.push(chunk);
+        }
+    }
+}
+
+impl fmt::Debug for StagingBelt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StagingBelt")
+            .field("chunk_size", &self.chunk_size)
+            .field("active_chunks", &self.active_chunks.len())
+            .field("closed_chunks", &self.closed_chunks.len())
+            .field("free_chunks", &self.free_chunks.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A chunk owned by a [`CpuWriteGpuReadBelt`], some of which may currently be lent
+/// out as a [`CpuWriteGpuReadBuffer`].
+struct ReadBeltChunk {
+    buffer: Arc<Buffer>,
+    size: BufferAddress,
+    offset: BufferAddress,
+    /// How many [`CpuWriteGpuReadBuffer`]s handed out of this chunk have not yet
+    /// been dropped. [`CpuWriteGpuReadBelt::finish`] must not unmap a chunk while
+    /// this is nonzero, since that would invalidate the mapped pointer those
+    /// handles still hold.
+    outstanding: Arc<AtomicUsize>,
+}
+
+/// A mapped, typed write view into a chunk of a [`CpuWriteGpuReadBelt`],
+/// sub-allocated to hold `count` values of `T`.
+///
+/// Created by [`CpuWriteGpuReadBelt::write_buffer`]. Fill it with [`Self::push`] or
+/// [`Self::extend_from_slice`], then use [`Self::buffer`] to schedule a
+/// `copy_buffer_to_buffer` into the buffer it is actually destined for.
+pub struct CpuWriteGpuReadBuffer<T: bytemuck::Pod> {
+    // Safety: this view borrows from `buffer`'s mapping. We keep `buffer` alive
+    // alongside it, and `outstanding` guarantees `CpuWriteGpuReadBelt::finish`
+    // won't unmap the chunk this view points into while this handle is alive;
+    // see `Drop`, which releases that guarantee.
+    view: BufferViewMut<'static>,
+    /// The range, in elements of `T`, of the view that has not yet been written.
+    unwritten_element_range: Range<usize>,
+    buffer: Arc<Buffer>,
+    offset: BufferAddress,
+    size: BufferAddress,
+    outstanding: Arc<AtomicUsize>,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod> Drop for CpuWriteGpuReadBuffer<T> {
+    fn drop(&mut self) {
+        self.outstanding.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl<T: bytemuck::Pod> CpuWriteGpuReadBuffer<T> {
+    /// Push a single value into the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no remaining space in the sub-allocation.
+    pub fn push(&mut self, value: T) {
+        let index = self.unwritten_element_range.start;
+        assert!(
+            index < self.unwritten_element_range.end,
+            "CpuWriteGpuReadBuffer is full"
+        );
+        bytemuck::cast_slice_mut::<u8, T>(&mut self.view)[index] = value;
+        self.unwritten_element_range.start += 1;
+    }
+
+    /// Push a slice of values into the buffer, clamping to the remaining space if
+    /// `values` would not otherwise fit.
+    pub fn extend_from_slice(&mut self, values: &[T]) {
+        let remaining = self.unwritten_element_range.end - self.unwritten_element_range.start;
+        let count = values.len().min(remaining);
+        let start = self.unwritten_element_range.start;
+        bytemuck::cast_slice_mut::<u8, T>(&mut self.view)[start..start + count]
+            .copy_from_slice(&values[..count]);
+        self.unwritten_element_range.start += count;
+    }
+
+    /// The backing buffer, and the byte offset and size of the region that was
+    /// sub-allocated for this write, for use in a `copy_buffer_to_buffer` call.
+    pub fn buffer(&self) -> (&Buffer, BufferAddress, BufferAddress) {
+        (&self.buffer, self.offset, self.size)
+    }
+}
+
+/// A sub-allocator from which many small, short-lived CPU-write/GPU-read buffers
+/// can be requested, similar in spirit to [`StagingBelt`] but handing back typed
+/// write views directly rather than a raw `&mut [u8]`.
+///
+/// Each returned [`CpuWriteGpuReadBuffer`] must be copied, via
+/// `copy_buffer_to_buffer`, into the buffer it is actually destined for; the belt's
+/// chunks are only intended to be read from as a copy source.
+pub struct CpuWriteGpuReadBelt {
+    chunk_size: BufferAddress,
+    active_chunks: Vec<ReadBeltChunk>,
+    closed_chunks: Vec<ReadBeltChunk>,
+    free_chunks: Vec<ReadBeltChunk>,
+    sender: mpsc::Sender<ReadBeltChunk>,
+    receiver: mpsc::Receiver<ReadBeltChunk>,
+}
+
+impl CpuWriteGpuReadBelt {
+    /// Create a new belt, sized as described in [`StagingBelt::new`].
+    pub fn new(chunk_size: BufferAddress) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        CpuWriteGpuReadBelt {
+            chunk_size,
+            active_chunks: Vec::new(),
+            closed_chunks: Vec::new(),
+            free_chunks: Vec::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Allocate a mapped buffer sized to hold `count` values of `T`, ready to be
+    /// filled through [`CpuWriteGpuReadBuffer::push`] or
+    /// [`CpuWriteGpuReadBuffer::extend_from_slice`].
+    ///
+    /// `device` is the device used to create the belt, and must be the same
+    /// device the eventual copy destination buffer was created with.
+    pub fn write_buffer<T: bytemuck::Pod>(
+        &mut self,
+        device: &Device,
+        count: usize,
+    ) -> CpuWriteGpuReadBuffer<T> {
+        let alignment = super::align_to(
+            std::mem::align_of::<T>() as BufferAddress,
+            crate::COPY_BUFFER_ALIGNMENT,
+        );
+        let size = (std::mem::size_of::<T>() * count) as BufferAddress;
+
+        let mut chunk = if let Some(index) = self
+            .active_chunks
+            .iter()
+            .position(|chunk| super::align_to(chunk.offset, alignment) + size <= chunk.size)
+        {
+            self.active_chunks.swap_remove(index)
+        } else {
+            self.receive_chunks();
+
+            if let Some(index) = self
+                .free_chunks
+                .iter()
+                .position(|chunk| super::align_to(chunk.offset, alignment) + size <= chunk.size)
+            {
+                self.free_chunks.swap_remove(index)
+            } else {
+                let chunk_size = self.chunk_size.max(size);
+                ReadBeltChunk {
+                    buffer: Arc::new(device.create_buffer(&BufferDescriptor {
+                        label: Some("CpuWriteGpuReadBelt chunk"),
+                        size: chunk_size,
+                        usage: BufferUsages::MAP_WRITE | BufferUsages::COPY_SRC,
+                        mapped_at_creation: true,
+                    })),
+                    size: chunk_size,
+                    offset: 0,
+                    outstanding: Arc::new(AtomicUsize::new(0)),
+                }
+            }
+        };
+
+        let offset = super::align_to(chunk.offset, alignment);
+        chunk.offset = offset + size;
+
+        let buffer = chunk.buffer.clone();
+        let outstanding = chunk.outstanding.clone();
+        outstanding.fetch_add(1, Ordering::Acquire);
+        self.active_chunks.push(chunk);
+
+        // Safety: `view` borrows from `buffer`'s mapping. We extend its lifetime to
+        // `'static`; `buffer` and `outstanding`, kept alongside it in
+        // `CpuWriteGpuReadBuffer`, together guarantee the mapping outlives every
+        // access through `view` — `finish` refuses to unmap a chunk whose
+        // `outstanding` count hasn't dropped back to zero.
+        let view: BufferViewMut<'static> = unsafe {
+            std::mem::transmute(buffer.slice(offset..offset + size).get_mapped_range_mut())
+        };
+
+        CpuWriteGpuReadBuffer {
+            view,
+            unwritten_element_range: 0..count,
+            buffer,
+            offset,
+            size,
+            outstanding,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    /// Unmap all active chunks, preparing them to be used in a submission.
+    ///
+    /// This must be called before the command encoder(s) that copy out of the
+    /// handed-out [`CpuWriteGpuReadBuffer`]s are submitted.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any [`CpuWriteGpuReadBuffer`] handed out by [`Self::write_buffer`]
+    /// since the last `finish` is still alive: unmapping its chunk out from under
+    /// it would leave it holding a dangling mapped pointer.
+    pub fn finish(&mut self) {
+        for chunk in self.active_chunks.drain(..) {
+            assert_eq!(
+                chunk.outstanding.load(Ordering::Acquire),
+                0,
+                "CpuWriteGpuReadBelt::finish called while a CpuWriteGpuReadBuffer \
+                 handed out from this belt is still alive; drop it first"
+            );
+            chunk.buffer.unmap();
+            self.closed_chunks.push(chunk);
+        }
+    }
+
+    /// Recall all closed chunks back to be reused, once the GPU is done reading
+    /// from them.
+    ///
+    /// This must only be called after the command encoder(s) that copy out of the
+    /// handed-out buffers are submitted.
+    pub fn recall(&mut self) {
+        self.receive_chunks();
+
+        for chunk in self.closed_chunks.drain(..) {
+            let sender = self.sender.clone();
+            chunk.buffer.slice(..).map_async(MapMode::Write, move |_| {
+                let _ = sender.send(chunk);
+            });
+        }
+    }
+
+    fn receive_chunks(&mut self) {
+        while let Ok(mut chunk) = self.receiver.try_recv() {
+            chunk.offset = 0;
+            self.free_chunks.push(chunk);
+        }
+    }
+}
+
+impl fmt::Debug for CpuWriteGpuReadBelt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CpuWriteGpuReadBelt")
+            .field("chunk_size", &self.chunk_size)
+            .field("active_chunks", &self.active_chunks.len())
+            .field("closed_chunks", &self.closed_chunks.len())
+            .field("free_chunks", &self.free_chunks.len())
+            .finish_non_exhaustive()
+    }
+}
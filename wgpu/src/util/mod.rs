@@ -3,11 +3,13 @@
 //! Nothing in this module is a part of the WebGPU API specification;
 //! they are unique to the `wgpu` library.
 
+mod arena;
 mod belt;
 mod device;
 mod encoder;
 mod indirect;
 mod init;
+mod typed;
 
 use std::ops::{Add, Rem, Sub};
 use std::sync::Arc;
@@ -17,11 +19,13 @@ use std::{
     ptr::copy_nonoverlapping,
 };
 
-pub use belt::StagingBelt;
+pub use arena::BufferArena;
+pub use belt::{CpuWriteGpuReadBelt, CpuWriteGpuReadBuffer, StagingBelt};
 pub use device::{BufferInitDescriptor, DeviceExt};
 pub use encoder::RenderEncoder;
 pub use indirect::*;
 pub use init::*;
+pub use typed::{BufferContents, Subbuffer, TypedBufferView, TypedBufferViewMut};
 
 /// Treat the given byte slice as a SPIR-V module.
 ///
@@ -73,10 +77,20 @@ pub fn make_spirv_raw(data: &[u8]) -> Cow<[u32]> {
     words
 }
 
+/// The row layout of a [`DownloadBuffer`] created by [`DownloadBuffer::read_texture`].
+#[derive(Debug, Clone, Copy)]
+struct TextureLayout {
+    bytes_per_row: u32,
+    unpadded_bytes_per_row: u32,
+    rows_per_image: u32,
+    layer_count: u32,
+}
+
 /// CPU accessible buffer used to download data back from the GPU.
 pub struct DownloadBuffer(
     Arc<super::Buffer>,
     Box<dyn crate::context::BufferMappedRange>,
+    Option<TextureLayout>,
 );
 
 impl DownloadBuffer {
@@ -120,9 +134,134 @@ impl DownloadBuffer {
                     download.data.as_ref(),
                     0..size,
                 );
-                callback(Ok(Self(download, mapped_range)));
+                callback(Ok(Self(download, mapped_range, None)));
+            });
+    }
+
+    /// Asynchronously read a texture back to the CPU.
+    ///
+    /// Unlike [`Self::read_buffer`], this handles the padding WebGPU requires
+    /// when copying a texture into a buffer: each row of the resulting
+    /// [`DownloadBuffer`] is padded up to a multiple of
+    /// [`super::COPY_BYTES_PER_ROW_ALIGNMENT`] bytes. Use
+    /// [`Self::into_tightly_packed`] to strip that padding back out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `format` is a combined depth-stencil format and `source.aspect`
+    /// is [`super::TextureAspect::All`] rather than a single plane; request
+    /// [`super::TextureAspect::DepthOnly`] or [`super::TextureAspect::StencilOnly`]
+    /// instead.
+    pub fn read_texture(
+        device: &super::Device,
+        queue: &super::Queue,
+        source: super::ImageCopyTexture,
+        size: super::Extent3d,
+        format: super::TextureFormat,
+        callback: impl FnOnce(Result<Self, super::BufferAsyncError>) + Send + 'static,
+    ) {
+        let (block_width, block_height) = format.block_dimensions();
+        let block_size = format
+            .block_copy_size(Some(source.aspect))
+            .expect("DownloadBuffer::read_texture does not support combined depth-stencil formats");
+
+        let blocks_per_row = (size.width + block_width - 1) / block_width;
+        let blocks_per_column = (size.height + block_height - 1) / block_height;
+
+        let unpadded_bytes_per_row = blocks_per_row * block_size;
+        let bytes_per_row = align_to(unpadded_bytes_per_row, super::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let buffer_size = bytes_per_row as super::BufferAddress
+            * blocks_per_column as super::BufferAddress
+            * size.depth_or_array_layers as super::BufferAddress;
+
+        let download = Arc::new(device.create_buffer(&super::BufferDescriptor {
+            size: buffer_size,
+            usage: super::BufferUsages::COPY_DST | super::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+            label: None,
+        }));
+
+        let mut encoder =
+            device.create_command_encoder(&super::CommandEncoderDescriptor { label: None });
+        encoder.copy_texture_to_buffer(
+            source,
+            super::ImageCopyBuffer {
+                buffer: &download,
+                layout: super::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(blocks_per_column),
+                },
+            },
+            size,
+        );
+        let command_buffer: super::CommandBuffer = encoder.finish();
+        queue.submit(Some(command_buffer));
+
+        download
+            .clone()
+            .slice(..)
+            .map_async(super::MapMode::Read, move |result| {
+                if let Err(e) = result {
+                    callback(Err(e));
+                    return;
+                }
+
+                let mapped_range = super::DynContext::buffer_get_mapped_range(
+                    &*download.context,
+                    &download.id,
+                    download.data.as_ref(),
+                    0..buffer_size,
+                );
+                let layout = TextureLayout {
+                    bytes_per_row,
+                    unpadded_bytes_per_row,
+                    rows_per_image: blocks_per_column,
+                    layer_count: size.depth_or_array_layers,
+                };
+                callback(Ok(Self(download, mapped_range, Some(layout))));
             });
     }
+
+    /// Copy this buffer's rows into a tightly packed `Vec<u8>`, stripping the
+    /// `bytes_per_row` alignment padding that [`Self::read_texture`] leaves in
+    /// place, across every layer for array/3D textures.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this buffer was not created by [`Self::read_texture`].
+    pub fn into_tightly_packed(self) -> Vec<u8> {
+        let layout = self
+            .2
+            .expect("into_tightly_packed requires a DownloadBuffer from read_texture");
+
+        let rows_per_layer = layout.rows_per_image as usize;
+        let layer_stride = layout.bytes_per_row as usize * rows_per_layer;
+        let total_rows = rows_per_layer * layout.layer_count as usize;
+
+        let mut packed = Vec::with_capacity(layout.unpadded_bytes_per_row as usize * total_rows);
+        for layer in 0..layout.layer_count as usize {
+            for row in 0..rows_per_layer {
+                let start = layer * layer_stride + row * layout.bytes_per_row as usize;
+                let end = start + layout.unpadded_bytes_per_row as usize;
+                packed.extend_from_slice(&self[start..end]);
+            }
+        }
+        packed
+    }
+
+    /// Reinterpret the downloaded bytes as `&[T]`, for reading back typed
+    /// compute results (e.g. storage-buffer outputs) without re-deriving
+    /// alignment and strides by hand.
+    ///
+    /// Returns a [`bytemuck::PodCastError`] rather than panicking if this
+    /// buffer's length or alignment don't evenly divide into `T`.
+    pub fn read_buffer_as<T: bytemuck::AnyBitPattern>(
+        &self,
+    ) -> Result<&[T], bytemuck::PodCastError> {
+        bytemuck::try_cast_slice(self)
+    }
 }
 
 impl std::ops::Deref for DownloadBuffer {
@@ -0,0 +1,310 @@
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+use std::ops::{Bound, RangeBounds};
+use std::sync::Arc;
+
+use super::{BindingResource, Buffer, BufferAddress, BufferBinding, BufferSize, BufferSlice};
+
+mod sealed {
+    pub trait Sealed {}
+    impl<T: bytemuck::Pod> Sealed for T {}
+    impl<T: bytemuck::Pod> Sealed for [T] {}
+}
+
+/// The element types a [`Subbuffer`] may be parameterized over: either a single
+/// `T: bytemuck::Pod` value, or a `[T]` slice of such values.
+///
+/// This trait is sealed and cannot be implemented outside of `wgpu`.
+pub trait BufferContents: sealed::Sealed {
+    /// The alignment a region holding `Self` must start at.
+    const ALIGN: BufferAddress;
+
+    /// Reinterpret `bytes` as `&Self`.
+    ///
+    /// Returns an error if `bytes`'s length or alignment don't match `Self`,
+    /// rather than panicking.
+    fn ref_from_bytes(bytes: &[u8]) -> Result<&Self, bytemuck::PodCastError>;
+
+    /// Reinterpret `bytes` as `&mut Self`.
+    ///
+    /// Returns an error if `bytes`'s length or alignment don't match `Self`,
+    /// rather than panicking.
+    fn mut_from_bytes(bytes: &mut [u8]) -> Result<&mut Self, bytemuck::PodCastError>;
+
+    /// The size, in bytes, that `self` occupies.
+    fn byte_len(&self) -> BufferAddress;
+
+    /// Whether `size` bytes is a valid length for a `Subbuffer<Self>`: exactly
+    /// `size_of::<T>()` for a single value, or an exact multiple of it for a
+    /// `[T]` slice.
+    fn is_valid_size(size: BufferAddress) -> bool;
+}
+
+impl<T: bytemuck::Pod> BufferContents for T {
+    const ALIGN: BufferAddress = align_of::<T>() as BufferAddress;
+
+    fn ref_from_bytes(bytes: &[u8]) -> Result<&Self, bytemuck::PodCastError> {
+        bytemuck::try_from_bytes(bytes)
+    }
+
+    fn mut_from_bytes(bytes: &mut [u8]) -> Result<&mut Self, bytemuck::PodCastError> {
+        bytemuck::try_from_bytes_mut(bytes)
+    }
+
+    fn byte_len(&self) -> BufferAddress {
+        size_of::<T>() as BufferAddress
+    }
+
+    fn is_valid_size(size: BufferAddress) -> bool {
+        size == size_of::<T>() as BufferAddress
+    }
+}
+
+impl<T: bytemuck::Pod> BufferContents for [T] {
+    const ALIGN: BufferAddress = align_of::<T>() as BufferAddress;
+
+    fn ref_from_bytes(bytes: &[u8]) -> Result<&Self, bytemuck::PodCastError> {
+        bytemuck::try_cast_slice(bytes)
+    }
+
+    fn mut_from_bytes(bytes: &mut [u8]) -> Result<&mut Self, bytemuck::PodCastError> {
+        bytemuck::try_cast_slice_mut(bytes)
+    }
+
+    fn byte_len(&self) -> BufferAddress {
+        std::mem::size_of_val(self) as BufferAddress
+    }
+
+    fn is_valid_size(size: BufferAddress) -> bool {
+        let elem = size_of::<T>() as BufferAddress;
+        elem != 0 && size % elem == 0
+    }
+}
+
+/// A type-safe, alignment-aware view over a region of a backing [`Buffer`],
+/// carrying the element type `T` the region is meant to hold.
+///
+/// A `Subbuffer<T>` guarantees, by construction, that its byte offset is a
+/// multiple of `T::ALIGN`, and that its size is an exact multiple of
+/// `size_of::<T>()` for slice element types, or exactly `size_of::<T>()`
+/// otherwise. [`BufferArena::allocate_bytes`](super::BufferArena::allocate_bytes)
+/// returns a `Subbuffer<[u8]>` for a plain untyped byte region, since any size
+/// is a valid multiple of `size_of::<u8>()`. Cloning a `Subbuffer` is cheap: it
+/// only clones the `Arc` to the backing buffer, so clones refer to the same
+/// region.
+pub struct Subbuffer<T: BufferContents + ?Sized> {
+    buffer: Arc<Buffer>,
+    offset: BufferAddress,
+    size: BufferAddress,
+    alignment: BufferAddress,
+    _marker: PhantomData<fn() -> Box<T>>,
+}
+
+impl<T: BufferContents + ?Sized> Clone for Subbuffer<T> {
+    fn clone(&self) -> Self {
+        Subbuffer {
+            buffer: self.buffer.clone(),
+            offset: self.offset,
+            size: self.size,
+            alignment: self.alignment,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: BufferContents + ?Sized> Subbuffer<T> {
+    /// Wrap `offset..offset + size` of `buffer` as a `Subbuffer<T>`, recording
+    /// `alignment` as the alignment this region was actually allocated at (which
+    /// may be coarser than `T::ALIGN`, e.g. a dynamic-offset binding alignment),
+    /// so [`BufferArena::free`](super::BufferArena::free) can recycle it
+    /// correctly without the caller having to re-derive that alignment by hand.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is not a multiple of `alignment`, if `alignment` is not
+    /// a multiple of `T::ALIGN`, or if `size` is not a valid byte length for `T`
+    /// (an exact multiple of `size_of::<T>()` for slice element types).
+    pub(crate) fn from_raw_parts(
+        buffer: Arc<Buffer>,
+        offset: BufferAddress,
+        size: BufferAddress,
+        alignment: BufferAddress,
+    ) -> Self {
+        assert_eq!(
+            alignment % T::ALIGN,
+            0,
+            "Subbuffer alignment {alignment} is not a multiple of {}",
+            T::ALIGN
+        );
+        assert_eq!(
+            offset % alignment,
+            0,
+            "Subbuffer offset {offset} is not aligned to {alignment}"
+        );
+        assert!(
+            T::is_valid_size(size),
+            "Subbuffer size {size} is not a valid byte length for this element type"
+        );
+        Subbuffer {
+            buffer,
+            offset,
+            size,
+            alignment,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The alignment this region was allocated at, as recorded by
+    /// [`Self::from_raw_parts`].
+    pub(crate) fn alignment(&self) -> BufferAddress {
+        self.alignment
+    }
+
+    /// The backing buffer this region was carved out of.
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// A cloned handle to the backing buffer this region was carved out of.
+    pub(crate) fn buffer_arc(&self) -> Arc<Buffer> {
+        self.buffer.clone()
+    }
+
+    /// The byte offset, within [`Self::buffer`], at which this region starts.
+    pub fn offset(&self) -> BufferAddress {
+        self.offset
+    }
+
+    /// The size, in bytes, of this region.
+    pub fn size(&self) -> BufferAddress {
+        self.size
+    }
+
+    /// A [`BufferSlice`] over exactly this region, for binding or mapping
+    /// through the untyped `wgpu` API.
+    pub fn as_buffer_slice(&self) -> BufferSlice<'_> {
+        self.buffer.slice(self.offset..self.offset + self.size)
+    }
+
+    /// A [`BindingResource`] binding exactly this region, for use in a
+    /// [`BindGroupEntry`](super::BindGroupEntry).
+    pub fn as_entire_binding(&self) -> BindingResource<'_> {
+        BindingResource::Buffer(BufferBinding {
+            buffer: &self.buffer,
+            offset: self.offset,
+            size: BufferSize::new(self.size),
+        })
+    }
+
+    /// Reinterpret the mapped bytes of this region as `&T`.
+    ///
+    /// Returns a [`bytemuck::PodCastError`] rather than panicking if the
+    /// buffer's mapped range doesn't reinterpret cleanly as `T` (this should
+    /// only happen if the region was constructed with a mismatched size).
+    pub fn read(&self) -> Result<TypedBufferView<'_, T>, bytemuck::PodCastError> {
+        let view = self.as_buffer_slice().get_mapped_range();
+        T::ref_from_bytes(&view)?;
+        Ok(TypedBufferView {
+            view,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Reinterpret the mapped bytes of this region as `&mut T`.
+    ///
+    /// Returns a [`bytemuck::PodCastError`] rather than panicking if the
+    /// buffer's mapped range doesn't reinterpret cleanly as `T`.
+    pub fn write(&self) -> Result<TypedBufferViewMut<'_, T>, bytemuck::PodCastError> {
+        let mut view = self.as_buffer_slice().get_mapped_range_mut();
+        T::mut_from_bytes(&mut view)?;
+        Ok(TypedBufferViewMut {
+            view,
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T: bytemuck::Pod> Subbuffer<[T]> {
+    /// Narrow this region to the elements in `range`, returning a new
+    /// `Subbuffer<[T]>` over the same backing buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of bounds for this region's element count.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Subbuffer<[T]> {
+        let elem = size_of::<T>() as BufferAddress;
+        let len = (self.size / elem) as usize;
+
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n + 1,
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => len,
+        };
+        assert!(start <= end && end <= len, "Subbuffer slice out of bounds");
+
+        let offset = self.offset + start as BufferAddress * elem;
+        // A narrower region only keeps the parent's alignment if its new offset
+        // still meets it; otherwise it is only guaranteed `T`'s own alignment.
+        let alignment = if offset % self.alignment == 0 {
+            self.alignment
+        } else {
+            <[T]>::ALIGN
+        };
+        Subbuffer {
+            buffer: self.buffer.clone(),
+            offset,
+            size: (end - start) as BufferAddress * elem,
+            alignment,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The number of `T` elements this region holds.
+    pub fn len(&self) -> usize {
+        (self.size / size_of::<T>() as BufferAddress) as usize
+    }
+
+    /// Whether this region holds zero elements.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+}
+
+/// A read-only typed view into a mapped [`Subbuffer<T>`], returned by
+/// [`Subbuffer::read`]. Deref's to `&T`.
+pub struct TypedBufferView<'a, T: BufferContents + ?Sized> {
+    view: super::BufferView<'a>,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<'a, T: BufferContents + ?Sized> std::ops::Deref for TypedBufferView<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        T::ref_from_bytes(&self.view).expect("validated in Subbuffer::read")
+    }
+}
+
+/// A writable typed view into a mapped [`Subbuffer<T>`], returned by
+/// [`Subbuffer::write`]. Deref's to `&mut T`.
+pub struct TypedBufferViewMut<'a, T: BufferContents + ?Sized> {
+    view: super::BufferViewMut<'a>,
+    _marker: PhantomData<&'a mut T>,
+}
+
+impl<'a, T: BufferContents + ?Sized> std::ops::Deref for TypedBufferViewMut<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        T::ref_from_bytes(&self.view).expect("validated in Subbuffer::write")
+    }
+}
+
+impl<'a, T: BufferContents + ?Sized> std::ops::DerefMut for TypedBufferViewMut<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        T::mut_from_bytes(&mut self.view).expect("validated in Subbuffer::write")
+    }
+}